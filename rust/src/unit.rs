@@ -1,29 +1,600 @@
-struct Value<'a>(f64, &'a BaseUnit);
+/// Number of independent base quantities in the SI.
+pub const NUM_BASE_DIMENSIONS: usize = 7;
+
+/// Exponents of the seven SI base quantities, in the order:
+/// length, time, mass, electric current, thermodynamic temperature,
+/// amount of substance, luminous intensity.
+pub type Dimensions = [i8; NUM_BASE_DIMENSIONS];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Value(pub f64, pub Dimensions);
+
+/// Error returned when attempting to add or subtract two `Value`s whose
+/// dimensions do not match.
+#[derive(Debug)]
+pub struct IncompatibleDimensions;
+
+impl std::fmt::Display for IncompatibleDimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot add or subtract values of different dimensions")
+    }
+}
+
+impl std::error::Error for IncompatibleDimensions {}
+
+/// Error returned when combining or raising dimension exponents would
+/// overflow the `i8` range used to store them.
+#[derive(Debug)]
+pub struct DimensionOverflow;
+
+impl std::fmt::Display for DimensionOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "dimension exponent overflowed")
+    }
+}
+
+impl std::error::Error for DimensionOverflow {}
+
+fn combine_dims(a: Dimensions, b: Dimensions, sign: i8) -> Result<Dimensions, DimensionOverflow> {
+    let mut out = [0i8; NUM_BASE_DIMENSIONS];
+    for i in 0..NUM_BASE_DIMENSIONS {
+        let scaled = b[i].checked_mul(sign).ok_or(DimensionOverflow)?;
+        out[i] = a[i].checked_add(scaled).ok_or(DimensionOverflow)?;
+    }
+    Ok(out)
+}
+
+impl std::ops::Add for Value {
+    type Output = Result<Value, IncompatibleDimensions>;
+
+    fn add(self, rhs: Value) -> Self::Output {
+        if self.1 != rhs.1 {
+            return Err(IncompatibleDimensions);
+        }
+        Ok(Value(self.0 + rhs.0, self.1))
+    }
+}
+
+impl std::ops::Sub for Value {
+    type Output = Result<Value, IncompatibleDimensions>;
+
+    fn sub(self, rhs: Value) -> Self::Output {
+        if self.1 != rhs.1 {
+            return Err(IncompatibleDimensions);
+        }
+        Ok(Value(self.0 - rhs.0, self.1))
+    }
+}
+
+// `Mul`/`Div` are fallible rather than unconditionally succeeding: combining
+// two dimension-exponent arrays can overflow the `i8` range used to store
+// them (e.g. raising an already-high-exponent unit to a further power), so
+// `Output` is `Result<Value, DimensionOverflow>` instead of a bare `Value`.
+// This is a deliberate deviation from an infallible `Mul`/`Div` so that
+// overflow surfaces as an error instead of panicking or silently wrapping.
+impl std::ops::Mul for Value {
+    type Output = Result<Value, DimensionOverflow>;
+
+    fn mul(self, rhs: Value) -> Self::Output {
+        Ok(Value(self.0 * rhs.0, combine_dims(self.1, rhs.1, 1)?))
+    }
+}
+
+impl std::ops::Div for Value {
+    type Output = Result<Value, DimensionOverflow>;
+
+    fn div(self, rhs: Value) -> Self::Output {
+        Ok(Value(self.0 / rhs.0, combine_dims(self.1, rhs.1, -1)?))
+    }
+}
+
+impl Value {
+    pub fn powi(self, n: i32) -> Result<Value, DimensionOverflow> {
+        let mut dims = [0i8; NUM_BASE_DIMENSIONS];
+        for (dim, exponent) in dims.iter_mut().zip(self.1.iter()) {
+            // Multiply in `i32` first so a large `n` is caught by the
+            // `i8::try_from` below rather than silently wrapping.
+            *dim = (*exponent as i32)
+                .checked_mul(n)
+                .and_then(|scaled| i8::try_from(scaled).ok())
+                .ok_or(DimensionOverflow)?;
+        }
+        Ok(Value(self.0.powi(n), dims))
+    }
+
+    /// Returns the canonical form of this value.
+    ///
+    /// Dimensions are stored as a fixed-size exponent vector indexed by
+    /// base quantity rather than as a list of factors, so repeated or
+    /// cancelled base units are already folded together and ordering is
+    /// already deterministic by construction: `Mul`/`Div` can never
+    /// produce two structurally different representations of the same
+    /// physical dimension. `unify` is therefore the identity, but is
+    /// provided so callers don't need to know that.
+    pub fn unify(self) -> Value {
+        self
+    }
+
+    pub fn as_runtime(&self) -> RuntimeUnit {
+        RuntimeUnit::from_dimensions(self.1)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let units = self.as_runtime().to_string();
+        if units.is_empty() {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "{} {}", self.0, units)
+        }
+    }
+}
+
+/// Error returned when attempting to convert a `Value` to a `Unit` whose
+/// dimensions don't match.
+#[derive(Debug)]
+pub struct IncompatibleUnits;
+
+impl std::fmt::Display for IncompatibleUnits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot convert between units of different dimensions")
+    }
+}
+
+impl std::error::Error for IncompatibleUnits {}
+
+impl Value {
+    /// Converts this value, expressed in coherent base units, to the
+    /// equivalent magnitude expressed in `target`, by rescaling the
+    /// scalar by `target`'s conversion factor. Fails if `target` has
+    /// different dimensions, since e.g. a length can't be expressed in
+    /// litres.
+    pub fn convert_to(&self, target: &Unit) -> Result<Value, IncompatibleUnits> {
+        if self.1 != target.def {
+            return Err(IncompatibleUnits);
+        }
+        Ok(Value(self.0 / target.factor, target.def))
+    }
+}
 
 trait Val {
     fn value(&self) -> Value;
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BaseUnit {
     Metre,
     Second,
     Kilogram,
+    Ampere,
+    Kelvin,
+    Mole,
+    Candela,
+}
+
+impl BaseUnit {
+    /// All base units, in the order their exponents appear in `Dimensions`.
+    const ALL: [BaseUnit; NUM_BASE_DIMENSIONS] = [
+        BaseUnit::Metre,
+        BaseUnit::Second,
+        BaseUnit::Kilogram,
+        BaseUnit::Ampere,
+        BaseUnit::Kelvin,
+        BaseUnit::Mole,
+        BaseUnit::Candela,
+    ];
+
+    /// The index of this base unit's quantity within a `Dimensions` array.
+    fn index(&self) -> usize {
+        match self {
+            BaseUnit::Metre => 0,
+            BaseUnit::Second => 1,
+            BaseUnit::Kilogram => 2,
+            BaseUnit::Ampere => 3,
+            BaseUnit::Kelvin => 4,
+            BaseUnit::Mole => 5,
+            BaseUnit::Candela => 6,
+        }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        let mut dims = [0i8; NUM_BASE_DIMENSIONS];
+        dims[self.index()] = 1;
+        dims
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            BaseUnit::Metre => "m",
+            BaseUnit::Second => "s",
+            BaseUnit::Kilogram => "kg",
+            BaseUnit::Ampere => "A",
+            BaseUnit::Kelvin => "K",
+            BaseUnit::Mole => "mol",
+            BaseUnit::Candela => "cd",
+        }
+    }
+
+    /// Conventional presentation order for rendering a compound unit
+    /// (mass, length, time, current, temperature, amount, luminosity),
+    /// e.g. `kg·m·s⁻²`. This is independent of `index()`, which instead
+    /// fixes each base unit's slot within `Dimensions` and must not
+    /// change to match it.
+    fn display_rank(&self) -> usize {
+        match self {
+            BaseUnit::Kilogram => 0,
+            BaseUnit::Metre => 1,
+            BaseUnit::Second => 2,
+            BaseUnit::Ampere => 3,
+            BaseUnit::Kelvin => 4,
+            BaseUnit::Mole => 5,
+            BaseUnit::Candela => 6,
+        }
+    }
+}
+
+/// A runtime description of a unit's dimensions, designed for indexing and
+/// debugging: a sorted list of `(BaseUnit, exponent)` pairs with any
+/// zero exponents dropped. Two values of the same physical dimension
+/// produce equal `RuntimeUnit`s, so they work as keys for grouping
+/// quantities by dimension.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RuntimeUnit(Vec<(BaseUnit, i8)>);
+
+impl RuntimeUnit {
+    fn from_dimensions(dims: Dimensions) -> RuntimeUnit {
+        let mut pairs: Vec<(BaseUnit, i8)> = BaseUnit::ALL
+            .into_iter()
+            .zip(dims)
+            .filter(|(_, exponent)| *exponent != 0)
+            .collect();
+        pairs.sort_by_key(|(base, _)| base.display_rank());
+        RuntimeUnit(pairs)
+    }
+}
+
+/// Renders an exponent using Unicode superscript digits, e.g. `-2` as `⁻²`.
+fn superscript(exponent: i8) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    let mut rendered = String::new();
+    if exponent < 0 {
+        rendered.push('⁻');
+    }
+    for digit in exponent.unsigned_abs().to_string().chars() {
+        rendered.push(DIGITS[digit.to_digit(10).unwrap() as usize]);
+    }
+    rendered
+}
+
+impl std::fmt::Display for RuntimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rendered: Vec<String> = self
+            .0
+            .iter()
+            .map(|(base, exponent)| {
+                if *exponent == 1 {
+                    base.symbol().to_string()
+                } else {
+                    format!("{}{}", base.symbol(), superscript(*exponent))
+                }
+            })
+            .collect();
+        write!(f, "{}", rendered.join("·"))
+    }
 }
 
 impl Val for BaseUnit {
     fn value(&self) -> Value {
-        Value(1.0, &self)
+        Value(1.0, self.dimensions())
     }
 }
 
-pub struct Unit<'a> {
+pub struct Unit {
     pub symbol: String,
     pub name: String,
-    pub def: &'a BaseUnit,
+    pub def: Dimensions,
+    /// The scalar magnitude of one of this unit, relative to the coherent
+    /// combination of base units with the same `def`. Plain SI units (a
+    /// bare metre, a newton) have a factor of `1.0`; `with_prefix` scales
+    /// it, and a non-coherent unit carries its own factor (e.g. `60.0` for
+    /// a minute, whose `def` is that of the second).
+    pub factor: f64,
 }
 
-impl Val for Unit<'_> {
+impl Val for Unit {
     fn value(&self) -> Value {
-        Value(1.0, self.def)
+        Value(self.factor, self.def)
+    }
+}
+
+/// An SI decimal prefix, or an IEC binary prefix, that scales a unit's
+/// magnitude without affecting its dimensions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Prefix {
+    Yocto,
+    Zepto,
+    Atto,
+    Femto,
+    Pico,
+    Nano,
+    Micro,
+    Milli,
+    Centi,
+    Deci,
+    Deca,
+    Hecto,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+    Peta,
+    Exa,
+    Zetta,
+    Yotta,
+    Kibi,
+    Mebi,
+    Gibi,
+    Tebi,
+    Pebi,
+    Exbi,
+}
+
+impl Prefix {
+    /// The factor by which this prefix scales a unit's magnitude.
+    fn factor(&self) -> f64 {
+        match self {
+            Prefix::Yocto => 1e-24,
+            Prefix::Zepto => 1e-21,
+            Prefix::Atto => 1e-18,
+            Prefix::Femto => 1e-15,
+            Prefix::Pico => 1e-12,
+            Prefix::Nano => 1e-9,
+            Prefix::Micro => 1e-6,
+            Prefix::Milli => 1e-3,
+            Prefix::Centi => 1e-2,
+            Prefix::Deci => 1e-1,
+            Prefix::Deca => 1e1,
+            Prefix::Hecto => 1e2,
+            Prefix::Kilo => 1e3,
+            Prefix::Mega => 1e6,
+            Prefix::Giga => 1e9,
+            Prefix::Tera => 1e12,
+            Prefix::Peta => 1e15,
+            Prefix::Exa => 1e18,
+            Prefix::Zetta => 1e21,
+            Prefix::Yotta => 1e24,
+            Prefix::Kibi => 2f64.powi(10),
+            Prefix::Mebi => 2f64.powi(20),
+            Prefix::Gibi => 2f64.powi(30),
+            Prefix::Tebi => 2f64.powi(40),
+            Prefix::Pebi => 2f64.powi(50),
+            Prefix::Exbi => 2f64.powi(60),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Prefix::Yocto => "y",
+            Prefix::Zepto => "z",
+            Prefix::Atto => "a",
+            Prefix::Femto => "f",
+            Prefix::Pico => "p",
+            Prefix::Nano => "n",
+            Prefix::Micro => "µ",
+            Prefix::Milli => "m",
+            Prefix::Centi => "c",
+            Prefix::Deci => "d",
+            Prefix::Deca => "da",
+            Prefix::Hecto => "h",
+            Prefix::Kilo => "k",
+            Prefix::Mega => "M",
+            Prefix::Giga => "G",
+            Prefix::Tera => "T",
+            Prefix::Peta => "P",
+            Prefix::Exa => "E",
+            Prefix::Zetta => "Z",
+            Prefix::Yotta => "Y",
+            Prefix::Kibi => "Ki",
+            Prefix::Mebi => "Mi",
+            Prefix::Gibi => "Gi",
+            Prefix::Tebi => "Ti",
+            Prefix::Pebi => "Pi",
+            Prefix::Exbi => "Ei",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Prefix::Yocto => "yocto",
+            Prefix::Zepto => "zepto",
+            Prefix::Atto => "atto",
+            Prefix::Femto => "femto",
+            Prefix::Pico => "pico",
+            Prefix::Nano => "nano",
+            Prefix::Micro => "micro",
+            Prefix::Milli => "milli",
+            Prefix::Centi => "centi",
+            Prefix::Deci => "deci",
+            Prefix::Deca => "deca",
+            Prefix::Hecto => "hecto",
+            Prefix::Kilo => "kilo",
+            Prefix::Mega => "mega",
+            Prefix::Giga => "giga",
+            Prefix::Tera => "tera",
+            Prefix::Peta => "peta",
+            Prefix::Exa => "exa",
+            Prefix::Zetta => "zetta",
+            Prefix::Yotta => "yotta",
+            Prefix::Kibi => "kibi",
+            Prefix::Mebi => "mebi",
+            Prefix::Gibi => "gibi",
+            Prefix::Tebi => "tebi",
+            Prefix::Pebi => "pebi",
+            Prefix::Exbi => "exbi",
+        }
+    }
+}
+
+impl Unit {
+    /// Returns the canonical dimension exponents of this unit, for the
+    /// same reason `Value::unify` is the identity: the exponent-vector
+    /// representation has no redundant or unordered state to collapse.
+    pub fn normalise(&self) -> Dimensions {
+        self.def
+    }
+
+    pub fn as_runtime(&self) -> RuntimeUnit {
+        RuntimeUnit::from_dimensions(self.def)
+    }
+
+    /// Returns a new unit equal to this one scaled by `prefix`, e.g.
+    /// `metre.with_prefix(Prefix::Kilo)` gives a kilometre. The dimension
+    /// exponents are unchanged; only `factor` and the rendered
+    /// symbol/name are affected.
+    pub fn with_prefix(&self, prefix: Prefix) -> Unit {
+        Unit {
+            symbol: format!("{}{}", prefix.symbol(), self.symbol),
+            name: format!("{}{}", prefix.name(), self.name),
+            def: self.def,
+            factor: self.factor * prefix.factor(),
+        }
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_runtime())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_unit_dimensions_set_a_single_exponent() {
+        assert_eq!(BaseUnit::Metre.dimensions(), [1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(BaseUnit::Kilogram.dimensions(), [0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn different_base_units_have_different_dimensions() {
+        assert_ne!(BaseUnit::Metre.dimensions(), BaseUnit::Second.dimensions());
+    }
+
+    #[test]
+    fn div_combines_dimensions_by_subtraction() {
+        let metres = Value(4.0, BaseUnit::Metre.dimensions());
+        let seconds = Value(2.0, BaseUnit::Second.dimensions());
+        let speed = (metres / seconds).unwrap();
+        assert_eq!(speed.0, 2.0);
+        assert_eq!(speed.1, [1, -1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn add_rejects_mismatched_dimensions() {
+        let metres = Value(1.0, BaseUnit::Metre.dimensions());
+        let seconds = Value(1.0, BaseUnit::Second.dimensions());
+        assert!((metres + seconds).is_err());
+    }
+
+    #[test]
+    fn powi_raises_every_exponent() {
+        let metres = Value(2.0, BaseUnit::Metre.dimensions());
+        let cubed = metres.powi(3).unwrap();
+        assert_eq!(cubed.0, 8.0);
+        assert_eq!(cubed.1, [3, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn powi_reports_overflow_instead_of_panicking() {
+        let per_square_metre = Value(2.0, [-2, 0, 0, 0, 0, 0, 0]);
+        assert!(per_square_metre.powi(100).is_err());
+    }
+
+    #[test]
+    fn unify_is_identity_and_equal_dimensions_compare_equal() {
+        let speed_a = (Value(4.0, BaseUnit::Metre.dimensions())
+            / Value(2.0, BaseUnit::Second.dimensions()))
+        .unwrap();
+        let speed_b = Value(2.0, [1, -1, 0, 0, 0, 0, 0]);
+        assert_eq!(speed_a.unify(), speed_b.unify());
+    }
+
+    #[test]
+    fn unit_normalise_returns_its_dimensions() {
+        let metre = Unit {
+            symbol: "m".to_string(),
+            name: "metre".to_string(),
+            def: BaseUnit::Metre.dimensions(),
+            factor: 1.0,
+        };
+        assert_eq!(metre.normalise(), metre.def);
+    }
+
+    #[test]
+    fn force_displays_in_conventional_si_order() {
+        // kg·m·s⁻², not the index order (length, time, mass, ...) that
+        // `Dimensions` itself uses.
+        let newton = Value(1.0, [1, -2, 1, 0, 0, 0, 0]);
+        assert_eq!(newton.as_runtime().to_string(), "kg·m·s⁻²");
+    }
+
+    #[test]
+    fn single_dimension_displays_without_exponent() {
+        let length = Value(1.0, BaseUnit::Metre.dimensions());
+        assert_eq!(length.as_runtime().to_string(), "m");
+    }
+
+    #[test]
+    fn dimensionless_value_displays_without_trailing_space() {
+        let ratio = (Value(10.0, BaseUnit::Metre.dimensions())
+            / Value(2.0, BaseUnit::Metre.dimensions()))
+        .unwrap();
+        assert_eq!(ratio.to_string(), "5");
+    }
+
+    fn metre() -> Unit {
+        Unit {
+            symbol: "m".to_string(),
+            name: "metre".to_string(),
+            def: BaseUnit::Metre.dimensions(),
+            factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn with_prefix_scales_factor_and_renders_symbol_and_name() {
+        let kilometre = metre().with_prefix(Prefix::Kilo);
+        assert_eq!(kilometre.factor, 1000.0);
+        assert_eq!(kilometre.symbol, "km");
+        assert_eq!(kilometre.name, "kilometre");
+        assert_eq!(kilometre.def, metre().def);
+    }
+
+    #[test]
+    fn prefixed_unit_value_participates_in_arithmetic() {
+        let kilometre = metre().with_prefix(Prefix::Kilo);
+        let hour_in_seconds = Value(3600.0, BaseUnit::Second.dimensions());
+        let speed = (kilometre.value() / hour_in_seconds).unwrap();
+        assert_eq!(speed.0, 1000.0 / 3600.0);
+        assert_eq!(speed.1, [1, -1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn convert_to_rescales_by_the_target_factor() {
+        let kilometre = metre().with_prefix(Prefix::Kilo);
+        let two_kilometres = Value(2000.0, BaseUnit::Metre.dimensions());
+        let converted = two_kilometres.convert_to(&kilometre).unwrap();
+        assert_eq!(converted.0, 2.0);
+        assert_eq!(converted.1, kilometre.def);
+    }
+
+    #[test]
+    fn convert_to_rejects_incompatible_dimensions() {
+        let kilometre = metre().with_prefix(Prefix::Kilo);
+        let duration = Value(1.0, BaseUnit::Second.dimensions());
+        assert!(duration.convert_to(&kilometre).is_err());
     }
 }